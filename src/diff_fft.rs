@@ -0,0 +1,72 @@
+//! FFT-accelerated replacement for the direct `O(N * tau_max)` loop in
+//! `diff_function`, used once `tau_max` grows large enough that the
+//! autocorrelation win outweighs the FFT setup cost.
+
+use num_traits::{Float, FromPrimitive};
+use realfft::RealFftPlanner;
+
+/// Computes the YIN difference function via the Wiener-Khinchin theorem:
+/// `d(tau) = sum(x_j^2) + sum(x_{j+tau}^2) - 2 * r(tau)`, where `r(tau)` is
+/// the cross-correlation of the fixed reference window `x[0..range_len]`
+/// against the full signal, obtained from
+/// `ifft(conj(fft(reference)) * fft(x))`. `diff_function` sums over that
+/// same fixed `range_len = n - tau_max` window for every `tau`, so the
+/// reference must stay fixed rather than sliding with `tau`. The two energy
+/// sums are prefix sums of `x^2`, so the whole computation is
+/// `O(N log N)` instead of `O(N * tau_max)`.
+pub(crate) fn diff_function_fft<F>(audio_sample: &[F], tau_max: usize) -> Vec<F>
+where
+    F: Float + realfft::FftNum + FromPrimitive,
+{
+    let n = audio_sample.len();
+    let mut diff = vec![F::zero(); tau_max];
+    let tau_max = std::cmp::min(n, tau_max);
+    let range_len = n - tau_max;
+    let fft_len = (2 * n).next_power_of_two();
+
+    let mut planner = RealFftPlanner::<F>::new();
+    let r2c = planner.plan_fft_forward(fft_len);
+    let c2r = planner.plan_fft_inverse(fft_len);
+
+    let mut reference = r2c.make_input_vec();
+    for (dst, src) in reference.iter_mut().zip(audio_sample[..range_len].iter()) {
+        *dst = *src;
+    }
+    let mut reference_spectrum = r2c.make_output_vec();
+    r2c.process(&mut reference, &mut reference_spectrum)
+        .expect("forward FFT of a zero-padded window should not fail");
+
+    let mut full = r2c.make_input_vec();
+    for (dst, src) in full.iter_mut().zip(audio_sample.iter()) {
+        *dst = *src;
+    }
+    let mut full_spectrum = r2c.make_output_vec();
+    r2c.process(&mut full, &mut full_spectrum)
+        .expect("forward FFT of a zero-padded window should not fail");
+
+    for (reference_bin, full_bin) in reference_spectrum.iter_mut().zip(full_spectrum.iter()) {
+        *reference_bin = reference_bin.conj() * *full_bin;
+    }
+
+    let mut cross_corr = c2r.make_output_vec();
+    c2r.process(&mut reference_spectrum, &mut cross_corr)
+        .expect("inverse FFT of a cross-power spectrum should not fail");
+
+    let norm = F::from_usize(fft_len).unwrap();
+    for value in cross_corr.iter_mut() {
+        *value = *value / norm;
+    }
+
+    let mut prefix_sq = vec![F::zero(); n + 1];
+    for (i, sample) in audio_sample.iter().enumerate() {
+        prefix_sq[i + 1] = prefix_sq[i] + *sample * *sample;
+    }
+    let energy_x = prefix_sq[range_len];
+
+    let two = F::from_usize(2).unwrap();
+    for tau in 1..tau_max {
+        let energy_shifted = prefix_sq[tau + range_len] - prefix_sq[tau];
+        diff[tau] = energy_x + energy_shifted - two * cross_corr[tau];
+    }
+    diff
+}
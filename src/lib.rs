@@ -1,14 +1,103 @@
 mod errors;
+pub mod stream;
+
+#[cfg(feature = "fft")]
+mod diff_fft;
+
+pub use stream::YinStream;
 
 use errors::UnknownValueError;
 use num_traits::Float;
 
+// Above this `tau_max`, the FFT autocorrelation's O(N log N) cost beats
+// both the serial and rayon-parallel O(N * tau_max) loops, so it takes
+// priority over `RAYON_TAU_MAX_THRESHOLD` whenever both features are on.
+#[cfg(feature = "fft")]
+const FFT_TAU_MAX_THRESHOLD: usize = 512;
+
+/// Bound shared by every `Yin`/`YinStream` method that runs detection,
+/// gated to pull in [`realfft::FftNum`] only when the `fft` feature needs
+/// it. Collapses what would otherwise be mirrored `fft`/non-`fft` impl
+/// blocks with identical bodies into one generic impl per type.
+#[cfg(not(feature = "fft"))]
+pub trait YinFloat:
+    Float
+    + Copy
+    + std::ops::AddAssign
+    + num_traits::FromPrimitive
+    + num_traits::ToPrimitive
+    + std::fmt::Display
+    + Send
+    + Sync
+{
+}
+
+#[cfg(not(feature = "fft"))]
+impl<F> YinFloat for F where
+    F: Float
+        + Copy
+        + std::ops::AddAssign
+        + num_traits::FromPrimitive
+        + num_traits::ToPrimitive
+        + std::fmt::Display
+        + Send
+        + Sync
+{
+}
+
+/// Bound shared by every `Yin`/`YinStream` method that runs detection,
+/// gated to pull in [`realfft::FftNum`] only when the `fft` feature needs
+/// it. Collapses what would otherwise be mirrored `fft`/non-`fft` impl
+/// blocks with identical bodies into one generic impl per type.
+#[cfg(feature = "fft")]
+pub trait YinFloat:
+    Float
+    + Copy
+    + std::ops::AddAssign
+    + num_traits::FromPrimitive
+    + num_traits::ToPrimitive
+    + std::fmt::Display
+    + realfft::FftNum
+    + Send
+    + Sync
+{
+}
+
+#[cfg(feature = "fft")]
+impl<F> YinFloat for F where
+    F: Float
+        + Copy
+        + std::ops::AddAssign
+        + num_traits::FromPrimitive
+        + num_traits::ToPrimitive
+        + std::fmt::Display
+        + realfft::FftNum
+        + Send
+        + Sync
+{
+}
+
 #[derive(Clone, Debug)]
 pub struct Yin<F> {
     threshold: F,
     tau_max: usize,
     tau_min: usize,
     sample_rate: usize,
+    fallback_to_min: bool,
+}
+
+/// A single pitch-detection result, carrying the refined `frequency`
+/// alongside how much to trust it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DetectionResult<F> {
+    /// The estimated fundamental frequency, in Hz.
+    pub frequency: F,
+    /// `1 - cmndf[tau]` at the chosen period: near `1.0` for a clean
+    /// periodic signal, low for noise. All-zero input has a CMNDF of `0`
+    /// at every period and so reports `1.0`; its `gain` is `0`.
+    pub probability: F,
+    /// RMS level of the input window, `sqrt(mean(x^2))`, clamped to `[0, 1]`.
+    pub gain: F,
 }
 
 impl<F> Yin<F>
@@ -29,16 +118,34 @@ where
             tau_max,
             tau_min,
             sample_rate,
+            fallback_to_min: false,
         }
     }
 
+    /// When no CMNDF bin dips below the absolute threshold, fall back to
+    /// the global minimum within `[tau_min, tau_max)` instead of erroring
+    /// out. The result's voicing probability is `1 - cmndf[tau]` at that
+    /// minimum, so it is at most `1 - threshold`; with a threshold of `0.0`
+    /// a clean signal can still report a probability near `1.0`. Off by
+    /// default, so strict callers keep today's error semantics.
+    pub fn with_fallback_to_min(mut self, fallback_to_min: bool) -> Self {
+        self.fallback_to_min = fallback_to_min;
+        self
+    }
+}
+
+impl<F> Yin<F>
+where
+    F: YinFloat,
+{
     pub fn estimate_freq(&self, audio_sample: &[F]) -> Result<F, Box<dyn std::error::Error>> {
-        let sample_frequency = compute_sample_frequency(
+        let (sample_frequency, _cmndf_value) = compute_sample_frequency(
             audio_sample,
             self.tau_min,
             self.tau_max,
             self.sample_rate,
             self.threshold,
+            self.fallback_to_min,
         );
 
         if sample_frequency.is_infinite() {
@@ -47,6 +154,49 @@ where
             Ok(sample_frequency)
         }
     }
+
+    /// Like [`Self::estimate_freq`], but also reports the voicing
+    /// probability and input gain alongside the frequency.
+    pub fn analyze(
+        &self,
+        audio_sample: &[F],
+    ) -> Result<DetectionResult<F>, Box<dyn std::error::Error>> {
+        let (frequency, cmndf_value) = compute_sample_frequency(
+            audio_sample,
+            self.tau_min,
+            self.tau_max,
+            self.sample_rate,
+            self.threshold,
+            self.fallback_to_min,
+        );
+
+        if frequency.is_infinite() {
+            return Err(Box::new(UnknownValueError {}));
+        }
+
+        Ok(DetectionResult {
+            frequency,
+            probability: F::one() - cmndf_value,
+            gain: rms_gain(audio_sample),
+        })
+    }
+}
+
+fn rms_gain<F>(audio_sample: &[F]) -> F
+where
+    F: Float + Copy + std::ops::AddAssign + num_traits::FromPrimitive,
+{
+    if audio_sample.is_empty() {
+        return F::zero();
+    }
+
+    let mut sum_squares = F::zero();
+    for sample in audio_sample {
+        sum_squares += *sample * *sample;
+    }
+
+    let mean_square = sum_squares / F::from_usize(audio_sample.len()).unwrap_or(F::one());
+    mean_square.sqrt().min(F::one())
 }
 
 fn diff_function<F: Float + std::ops::AddAssign>(audio_sample: &[F], tau_max: usize) -> Vec<F> {
@@ -61,6 +211,59 @@ fn diff_function<F: Float + std::ops::AddAssign>(audio_sample: &[F], tau_max: us
     diff_function
 }
 
+// Above this `tau_max`, each independent tau-slot is enough work that
+// splitting them across threads beats the serial loop. Kept below
+// `FFT_TAU_MAX_THRESHOLD` so the two compose: once `tau_max` reaches the
+// FFT threshold its O(N log N) cost wins outright, but everything between
+// the two thresholds still benefits from the rayon-parallel loop instead
+// of silently falling through to the serial one.
+#[cfg(feature = "rayon")]
+const RAYON_TAU_MAX_THRESHOLD: usize = 256;
+
+// Picks the rayon-parallel difference function for large `tau_max` and
+// falls back to the serial loop otherwise.
+fn compute_diff_function<F>(audio_sample: &[F], tau_max: usize) -> Vec<F>
+where
+    F: Float + Copy + std::ops::AddAssign + Send + Sync,
+{
+    #[cfg(feature = "rayon")]
+    {
+        if tau_max >= RAYON_TAU_MAX_THRESHOLD {
+            return diff_function_parallel(audio_sample, tau_max);
+        }
+    }
+    diff_function(audio_sample, tau_max)
+}
+
+// Parallel counterpart to `diff_function`: each `tau` writes an
+// independent slot, so the outer loop is embarrassingly parallel.
+#[cfg(feature = "rayon")]
+fn diff_function_parallel<F>(audio_sample: &[F], tau_max: usize) -> Vec<F>
+where
+    F: Float + Copy + std::ops::AddAssign + Send + Sync,
+{
+    use rayon::prelude::*;
+
+    let mut diff_function = vec![F::zero(); tau_max];
+    let tau_max = std::cmp::min(audio_sample.len(), tau_max);
+    let range_len = audio_sample.len() - tau_max;
+
+    diff_function[1..tau_max]
+        .par_iter_mut()
+        .enumerate()
+        .for_each(|(offset, slot)| {
+            let tau = offset + 1;
+            let mut sum = F::zero();
+            for j in 0..range_len {
+                let tmp = audio_sample[j] - audio_sample[j + tau];
+                sum += tmp * tmp;
+            }
+            *slot = sum;
+        });
+
+    diff_function
+}
+
 fn cmndf<F>(raw_diff: &[F]) -> Vec<F>
 where
     F: Float + Copy + std::ops::AddAssign,
@@ -87,6 +290,7 @@ fn compute_diff_min<F: Float>(
     min_tau: usize,
     max_tau: usize,
     harm_threshold: F,
+    fallback_to_min: bool,
 ) -> usize {
     let mut tau = min_tau;
     while tau < max_tau {
@@ -98,34 +302,133 @@ fn compute_diff_min<F: Float>(
         }
         tau += 1;
     }
-    0
+
+    if fallback_to_min {
+        global_min_tau(diff_fn, min_tau, max_tau)
+    } else {
+        0
+    }
+}
+
+// No bin crossed the absolute threshold; pick the least-aperiodic tau in
+// range instead of reporting an unknown value.
+fn global_min_tau<F: Float>(diff_fn: &[F], min_tau: usize, max_tau: usize) -> usize {
+    (min_tau..max_tau)
+        .min_by(|&a, &b| diff_fn[a].partial_cmp(&diff_fn[b]).unwrap())
+        .unwrap_or(0)
+}
+
+// Refines an integer-period estimate by fitting a parabola through the
+// CMNDF values around `tau`, so the result isn't limited to `sample_rate /
+// integer` bins. Falls back to the integer `tau` at the window edges or
+// when the parabola is degenerate (zero denominator).
+fn parabolic_interpolation<F>(diff_fn: &[F], tau: usize, tau_min: usize, tau_max: usize) -> F
+where
+    F: Float + Copy + num_traits::FromPrimitive,
+{
+    let tau_f = F::from_usize(tau).unwrap_or(F::zero());
+
+    if tau <= tau_min || tau + 1 >= tau_max {
+        return tau_f;
+    }
+
+    let d_prev = diff_fn[tau - 1];
+    let d_curr = diff_fn[tau];
+    let d_next = diff_fn[tau + 1];
+
+    let denominator = (d_curr + d_curr) - d_next - d_prev;
+    if denominator.is_zero() {
+        return tau_f;
+    }
+
+    tau_f + (d_next - d_prev) / (denominator + denominator)
 }
 
-fn convert_to_frequency<F>(sample_period: usize, sample_rate: usize) -> F
+fn convert_to_frequency<F>(sample_period: F, sample_rate: usize) -> F
 where
     F: Float + Copy + num_traits::FromPrimitive,
 {
     let sample_rate_f = F::from_usize(sample_rate).unwrap_or(F::zero());
-    let sample_period_f = F::from_usize(sample_period).unwrap_or(F::zero());
 
-    sample_rate_f / sample_period_f
+    sample_rate_f / sample_period
+}
+
+// Returns the estimated frequency together with the CMNDF value at the
+// chosen period, so callers can derive a voicing probability from it.
+#[cfg(not(feature = "fft"))]
+pub fn compute_sample_frequency<F>(
+    audio_sample: &[F],
+    tau_min: usize,
+    tau_max: usize,
+    sample_rate: usize,
+    threshold: F,
+    fallback_to_min: bool,
+) -> (F, F)
+where
+    F: Float + Copy + std::ops::AddAssign + num_traits::FromPrimitive + Send + Sync,
+{
+    let diff_fn = compute_diff_function(audio_sample, tau_max);
+    finish_sample_frequency(
+        &diff_fn,
+        tau_min,
+        tau_max,
+        sample_rate,
+        threshold,
+        fallback_to_min,
+    )
 }
 
-// should return a tau that gives the # of elements of offset in a given sample
+// FFT variant: picks the FFT autocorrelation path for large `tau_max` and
+// falls back to the direct loop otherwise, per `FFT_TAU_MAX_THRESHOLD`.
+#[cfg(feature = "fft")]
 pub fn compute_sample_frequency<F>(
     audio_sample: &[F],
     tau_min: usize,
     tau_max: usize,
     sample_rate: usize,
     threshold: F,
-) -> F
+    fallback_to_min: bool,
+) -> (F, F)
+where
+    F: Float
+        + Copy
+        + std::ops::AddAssign
+        + num_traits::FromPrimitive
+        + realfft::FftNum
+        + Send
+        + Sync,
+{
+    let diff_fn = if tau_max >= FFT_TAU_MAX_THRESHOLD {
+        diff_fft::diff_function_fft(audio_sample, tau_max)
+    } else {
+        compute_diff_function(audio_sample, tau_max)
+    };
+    finish_sample_frequency(
+        &diff_fn,
+        tau_min,
+        tau_max,
+        sample_rate,
+        threshold,
+        fallback_to_min,
+    )
+}
+
+fn finish_sample_frequency<F>(
+    diff_fn: &[F],
+    tau_min: usize,
+    tau_max: usize,
+    sample_rate: usize,
+    threshold: F,
+    fallback_to_min: bool,
+) -> (F, F)
 where
     F: Float + Copy + std::ops::AddAssign + num_traits::FromPrimitive,
 {
-    let diff_fn = diff_function(audio_sample, tau_max);
-    let cmndf = cmndf(&diff_fn);
-    let sample_period = compute_diff_min(&cmndf, tau_min, tau_max, threshold);
-    convert_to_frequency(sample_period, sample_rate)
+    let cmndf = cmndf(diff_fn);
+    let tau = compute_diff_min(&cmndf, tau_min, tau_max, threshold, fallback_to_min);
+    let cmndf_value = cmndf[tau];
+    let sample_period = parabolic_interpolation(&cmndf, tau, tau_min, tau_max);
+    (convert_to_frequency(sample_period, sample_rate), cmndf_value)
 }
 
 #[cfg(test)]
@@ -146,7 +449,7 @@ mod tests {
         let sample = produce_sample(12, 4.0, 0.0);
         let yin = Yin::init(0.1, 2.0, 5.0, 12);
         let computed_frequency = yin.estimate_freq(&sample).unwrap();
-        assert_eq!(computed_frequency, 4.0);
+        assert!((computed_frequency - 4.0).abs() < 0.2);
     }
 
     #[test]
@@ -154,7 +457,7 @@ mod tests {
         let sample = produce_sample(44100, 20.0, 0.0);
         let yin = Yin::init(0.1, 10.0, 100.0, 44100);
         let computed_frequency = yin.estimate_freq(&sample).unwrap();
-        assert_eq!(computed_frequency, 20.0);
+        assert!((computed_frequency - 20.0).abs() < 0.01);
     }
 
     #[test]
@@ -163,7 +466,7 @@ mod tests {
         let yin = Yin::init(0.1, 3000.0, 5000.0, 44100);
         let computed_frequency = yin.estimate_freq(&sample).unwrap();
         let difference = computed_frequency - 4000.0;
-        assert!(difference.abs() < 50.0);
+        assert!(difference.abs() < 20.0);
     }
 
     #[test]
@@ -171,7 +474,119 @@ mod tests {
         let sample = produce_sample(44100, 441.0, 0.0);
         let yin = Yin::init(0.1, 300.0, 500.0, 44100);
         let computed_frequency = yin.estimate_freq(&sample).unwrap();
-        assert_eq!(computed_frequency, 441.0);
+        assert!((computed_frequency - 441.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn analyze_reports_high_probability_for_clean_sine() {
+        let sample = produce_sample(44100, 441.0, 0.0);
+        let yin = Yin::init(0.1, 300.0, 500.0, 44100);
+        let result = yin.analyze(&sample).unwrap();
+        assert!((result.frequency - 441.0).abs() < 0.1);
+        assert!(result.probability > 0.9);
+        assert!(result.gain > 0.0 && result.gain <= 1.0);
+    }
+
+    #[test]
+    fn strict_threshold_miss_errors_without_fallback() {
+        let sample = produce_sample(44100, 441.0, 0.0);
+        let yin = Yin::init(0.0, 300.0, 500.0, 44100);
+        assert!(yin.estimate_freq(&sample).is_err());
+    }
+
+    #[test]
+    fn threshold_miss_falls_back_to_global_min_when_enabled() {
+        let sample = produce_sample(44100, 441.0, 0.0);
+        let yin = Yin::init(0.0, 300.0, 500.0, 44100).with_fallback_to_min(true);
+        let result = yin.analyze(&sample).unwrap();
+        // A threshold of 0.0 can never be satisfied, but the global minimum
+        // still lands on the true pitch for a clean signal like this one.
+        assert!((result.frequency - 441.0).abs() < 0.1);
+        assert!(result.probability > 0.9);
+    }
+
+    #[cfg(feature = "fft")]
+    #[test]
+    fn fft_diff_function_matches_direct_diff_function() {
+        let sample = produce_sample(44100, 441.0, 0.0);
+        let tau_max = 1000;
+
+        let direct = diff_function(&sample, tau_max);
+        let fft = diff_fft::diff_function_fft(&sample, tau_max);
+
+        for (direct_value, fft_value) in direct.iter().zip(fft.iter()) {
+            assert!((direct_value - fft_value).abs() < 1e-6);
+        }
+    }
+
+    #[cfg(feature = "fft")]
+    #[test]
+    fn fft_path_produces_same_frequency_as_direct_path() {
+        let sample = produce_sample(44100, 441.0, 0.0);
+        let yin = Yin::init(0.1, 50.0, 500.0, 44100);
+        let computed_frequency = yin.estimate_freq(&sample).unwrap();
+        assert!((computed_frequency - 441.0).abs() < 0.1);
+
+        let direct_diff = diff_function(&sample, yin.tau_max);
+        let (direct_frequency, _) = finish_sample_frequency(
+            &direct_diff,
+            yin.tau_min,
+            yin.tau_max,
+            yin.sample_rate,
+            yin.threshold,
+            false,
+        );
+        assert!((computed_frequency - direct_frequency).abs() < 1e-6);
+    }
+
+    // `diff_function_fft` used to allocate its output with the *clamped*
+    // `tau_max` instead of the original one, so a window shorter than
+    // `tau_max` (here `tau_max = 882`, well above `FFT_TAU_MAX_THRESHOLD`)
+    // produced a too-short `diff_fn` and panicked in `compute_diff_min`
+    // instead of yielding the same graceful error as the non-fft path.
+    #[cfg(feature = "fft")]
+    #[test]
+    fn fft_path_errors_instead_of_panicking_on_short_window() {
+        let short_sample = vec![0.5_f64; 100];
+        let yin = Yin::init(0.0, 50.0, 500.0, 44100);
+        assert!(yin.estimate_freq(&short_sample).is_err());
+    }
+
+    // Same undersized-`diff_fn` bug, reached through the fallback path
+    // instead of the plain error path: `with_fallback_to_min` is supposed
+    // to never fail, so it must not panic here either.
+    #[cfg(feature = "fft")]
+    #[test]
+    fn fft_path_fallback_does_not_panic_on_short_window() {
+        let short_sample = vec![0.5_f64; 100];
+        let yin = Yin::init(0.0, 50.0, 500.0, 44100).with_fallback_to_min(true);
+        assert!(yin.analyze(&short_sample).is_ok());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_diff_function_matches_serial_diff_function() {
+        let sample = produce_sample(44100, 441.0, 0.0);
+        let tau_max = 4000;
+
+        let serial = diff_function(&sample, tau_max);
+        let parallel = diff_function_parallel(&sample, tau_max);
+
+        assert_eq!(serial, parallel);
+    }
+
+    // With both features on, `compute_sample_frequency`'s fft branch only
+    // falls through to `compute_diff_function` for `tau_max <
+    // FFT_TAU_MAX_THRESHOLD`, so this is the only range where the rayon
+    // path is reachable at all. `freq_min` is chosen to land `tau_max`
+    // between the two thresholds.
+    #[cfg(all(feature = "fft", feature = "rayon"))]
+    #[test]
+    fn rayon_branch_is_reachable_below_fft_threshold() {
+        let sample = produce_sample(44100, 200.0, 0.0);
+        let yin = Yin::init(0.1, 150.0, 2000.0, 44100);
+        let computed_frequency = yin.estimate_freq(&sample).unwrap();
+        assert!((computed_frequency - 200.0).abs() < 0.5);
     }
 
     #[test]
@@ -188,6 +603,6 @@ mod tests {
             }
         }
         let freq = estimator.estimate_freq(&example).unwrap();
-        assert_eq!(freq, 20.0);
+        assert!((freq - 20.0).abs() < 1.0);
     }
 }
@@ -0,0 +1,196 @@
+//! Stateful, real-time-friendly wrapper around [`Yin`](crate::Yin) that
+//! slides an analysis window over an incoming stream of sample blocks,
+//! the way a live-audio callback buffers and hops across input.
+
+use crate::{DetectionResult, Yin, YinFloat};
+
+/// Pushes arbitrarily sized sample blocks and emits an
+/// `Option<DetectionResult>` (see [`Self::push`]) each time a full
+/// `hop_size` advances past the internal `window_len` ring buffer, reusing
+/// the wrapped [`Yin`] estimator on each hop.
+#[derive(Clone, Debug)]
+pub struct YinStream<F> {
+    yin: Yin<F>,
+    window_len: usize,
+    hop_size: usize,
+    // Circular storage: `head` is the index of the oldest sample (and the
+    // next write position), so a single write + index wrap per incoming
+    // sample is enough — no per-sample shifting of the whole window.
+    buffer: Vec<F>,
+    head: usize,
+    // Reused scratch space holding the window in chronological order,
+    // rebuilt from `buffer` once per completed hop (not per sample) since
+    // `Yin::analyze` needs a linear, oldest-to-newest slice.
+    scratch: Vec<F>,
+    filled: usize,
+    since_last_hop: usize,
+}
+
+impl<F> YinStream<F>
+where
+    F: YinFloat,
+{
+    pub fn new(yin: Yin<F>, window_len: usize, hop_size: usize) -> Self {
+        Self {
+            yin,
+            window_len,
+            hop_size,
+            buffer: vec![F::zero(); window_len],
+            head: 0,
+            scratch: vec![F::zero(); window_len],
+            filled: 0,
+            since_last_hop: 0,
+        }
+    }
+
+    pub fn window_len(&self) -> usize {
+        self.window_len
+    }
+
+    pub fn hop_size(&self) -> usize {
+        self.hop_size
+    }
+
+    /// Drops any buffered samples, as if the stream had just been created.
+    pub fn reset(&mut self) {
+        self.buffer.iter_mut().for_each(|sample| *sample = F::zero());
+        self.head = 0;
+        self.filled = 0;
+        self.since_last_hop = 0;
+    }
+
+    // Writes one sample into the ring buffer and reports whether a full
+    // hop has now elapsed over a full window, i.e. whether the caller
+    // should run the detector on the current buffer.
+    fn advance(&mut self, sample: F) -> bool {
+        self.buffer[self.head] = sample;
+        self.head = (self.head + 1) % self.window_len;
+
+        if self.filled < self.window_len {
+            self.filled += 1;
+        }
+        self.since_last_hop += 1;
+
+        if self.filled == self.window_len && self.since_last_hop >= self.hop_size {
+            self.since_last_hop = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Linearizes the circular buffer into `scratch`, oldest sample first:
+    // `head` already points at the oldest sample once the window has
+    // filled, so it's just the two halves swapped. A separate method (as
+    // opposed to returning `&self.scratch` directly) so the `&mut self`
+    // borrow ends here, letting callers immediately borrow `self.yin` and
+    // `self.scratch` immutably side by side.
+    fn fill_ordered_window(&mut self) {
+        let (before_head, from_head) = self.buffer.split_at(self.head);
+        self.scratch[..from_head.len()].copy_from_slice(from_head);
+        self.scratch[from_head.len()..].copy_from_slice(before_head);
+    }
+}
+
+impl<F> YinStream<F>
+where
+    F: YinFloat,
+{
+    /// Feeds a block of samples into the ring buffer, running the detector
+    /// once per full `hop_size` advance. Returns one entry per hop
+    /// completed by this call, in order; usually zero or one for block
+    /// sizes close to `hop_size`, but can be more for large blocks. An
+    /// entry is `None` when that hop's window didn't cross the detection
+    /// threshold, so callers can still tell a skipped hop from a missed one.
+    pub fn push(&mut self, block: &[F]) -> Vec<Option<DetectionResult<F>>> {
+        let mut results = Vec::new();
+
+        for &sample in block {
+            if self.advance(sample) {
+                self.fill_ordered_window();
+                results.push(self.yin.analyze(&self.scratch).ok());
+            }
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dasp::{signal, Signal};
+
+    fn produce_sample(sample_rate: usize, frequency: f64, count: usize) -> Vec<f64> {
+        let mut signal = signal::rate(sample_rate as f64).const_hz(frequency).sine();
+        (0..count).map(|_| signal.next()).collect()
+    }
+
+    #[test]
+    fn emits_one_result_per_hop() {
+        let yin = Yin::init(0.1, 300.0, 500.0, 44100);
+        let mut stream = YinStream::new(yin, 1024, 512);
+
+        // One continuous signal, split across pushes so the hop doesn't
+        // splice in a phase discontinuity that would throw off the detector.
+        let samples = produce_sample(44100, 441.0, 1024 + 512);
+
+        // The first full window already produces a result...
+        let first_results = stream.push(&samples[..1024]);
+        assert_eq!(first_results.len(), 1);
+        assert!((first_results[0].unwrap().frequency - 441.0).abs() < 0.1);
+
+        // ...and each subsequent full hop produces exactly one more.
+        let results = stream.push(&samples[1024..]);
+        assert_eq!(results.len(), 1);
+        assert!((results[0].unwrap().frequency - 441.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn detects_correctly_across_many_buffer_wraps() {
+        // Each hop moves `head` a quarter of the way around the ring buffer
+        // (wrapping back to 0 after four hops), so pushing several hops in a
+        // row exercises `fill_ordered_window` with `head` in the middle of
+        // the buffer, not just the `head == 0` boundary case.
+        let yin = Yin::init(0.1, 300.0, 500.0, 44100);
+        let mut stream = YinStream::new(yin, 1024, 256);
+
+        let samples = produce_sample(44100, 441.0, 1024 + 256 * 5);
+        let results = stream.push(&samples);
+
+        assert_eq!(results.len(), 6);
+        for result in results {
+            assert!((result.unwrap().frequency - 441.0).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn reset_clears_buffered_state() {
+        let yin = Yin::init(0.1, 300.0, 500.0, 44100);
+        let mut stream = YinStream::new(yin, 1024, 512);
+
+        stream.push(&produce_sample(44100, 441.0, 1024));
+        stream.reset();
+
+        let partial = produce_sample(44100, 441.0, 512);
+        assert!(stream.push(&partial).is_empty());
+    }
+
+    #[test]
+    fn missed_hop_reports_none_instead_of_being_dropped() {
+        // A strict threshold with no fallback can miss on unvoiced/silent
+        // input; the hop still completed, so it must show up as `None`
+        // rather than vanish from the result count.
+        let yin = Yin::init(0.0, 300.0, 500.0, 44100);
+        let mut stream = YinStream::new(yin, 1024, 512);
+
+        let silence = vec![0.0; 1024 + 512];
+        let first_results = stream.push(&silence[..1024]);
+        assert_eq!(first_results.len(), 1);
+        assert!(first_results[0].is_none());
+
+        let results = stream.push(&silence[1024..]);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_none());
+    }
+}